@@ -1,20 +1,31 @@
+use std::cell::Cell;
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::ffi::{c_void, CString};
 use std::fs::{self, File};
 use std::io::Read;
 use std::os::raw::c_char;
 use std::path::Path;
+use std::rc::Rc;
 
 use libarchive_sys as ffi;
 
+use crate::callback::open_with_reader;
+use crate::extract::{new_disk_writer, rebase_pathname, write_entry_to_disk};
+use crate::metadata::{copy_stat, copy_xattrs, major_minor};
 use crate::Entry;
 use crate::Error;
+use crate::ExtractOptions;
 use crate::Result;
 
 pub struct Archive {
     underlying: *mut ffi::archive,
     block_size: usize,
     close_read: bool,
+    // (device, inode) of every regular file with more than one link already
+    // appended, so that later entries pointing at the same inode can be
+    // written out as hardlinks instead of being duplicated.
+    inodes: HashMap<(u64, u64), String>,
 }
 
 impl Archive {
@@ -26,7 +37,13 @@ impl Archive {
         let file = path.to_string_lossy();
         let file = CString::new(file.as_bytes()).unwrap();
 
-        let block_size: usize = if cfg!(unix) {
+        let block_size = Archive::block_size_of(path)?;
+
+        Archive::open_filename(file.as_ptr(), block_size)
+    }
+
+    pub(crate) fn block_size_of(path: &Path) -> Result<usize> {
+        let block_size = if cfg!(unix) {
             use std::os::unix::fs::MetadataExt;
             let meta = fs::metadata(path)?;
             let block_size = meta.blksize();
@@ -35,13 +52,69 @@ impl Archive {
             Archive::DEFAULT_BLOCK_SIZE
         };
 
-        Archive::open_filename(file.as_ptr(), block_size)
+        Ok(block_size)
+    }
+
+    pub(crate) fn from_raw_read(
+        underlying: *mut ffi::archive,
+        block_size: usize,
+    ) -> Archive {
+        Archive {
+            underlying,
+            block_size,
+            close_read: true,
+            inodes: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn from_raw_write(underlying: *mut ffi::archive) -> Archive {
+        Archive {
+            underlying,
+            block_size: Archive::DEFAULT_BLOCK_SIZE,
+            close_read: false,
+            inodes: HashMap::new(),
+        }
     }
 
     pub fn stdin() -> Result<Archive> {
         Archive::open_filename(std::ptr::null(), Archive::DEFAULT_BLOCK_SIZE)
     }
 
+    /// Reads an archive out of any `Read` implementation (a socket, a
+    /// `Cursor`, a pipe, ...) instead of a filename, for callers who already
+    /// have the bytes in hand and don't want to spill them to a temp file.
+    pub fn from_reader<R: Read + 'static>(reader: R) -> Result<Archive> {
+        let block_size = Archive::DEFAULT_BLOCK_SIZE;
+
+        unsafe {
+            let archive = ffi::archive_read_new();
+
+            if archive.is_null() {
+                return Err(Error::new("archive allocation error"));
+            }
+
+            ffi::archive_read_support_filter_all(archive);
+            ffi::archive_read_support_format_all(archive);
+
+            match open_with_reader(archive, reader, block_size) {
+                ffi::fix::ARCHIVE_OK => {
+                    Ok(Archive::from_raw_read(archive, block_size))
+                }
+                _ => {
+                    let error = Error::from_archive(archive);
+                    ffi::archive_read_free(archive);
+                    Err(error)
+                }
+            }
+        }
+    }
+
+    /// Reads an archive out of an in-memory buffer. A thin convenience over
+    /// `from_reader` for the common case of already-decoded bytes.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Archive> {
+        Archive::from_reader(std::io::Cursor::new(bytes))
+    }
+
     pub fn create<P: AsRef<Path>>(path: P) -> Result<Archive> {
         let path = path.as_ref();
 
@@ -71,16 +144,7 @@ impl Archive {
                 ffi::archive_write_open_filename(archive, file.as_ptr());
 
             match result {
-                ffi::fix::ARCHIVE_OK => {
-                    let archive = Archive {
-                        underlying: archive,
-                        block_size,
-                        close_read: false,
-                    };
-
-                    Ok(archive)
-                }
-
+                ffi::fix::ARCHIVE_OK => Ok(Archive::from_raw_write(archive)),
                 _ => Err(Error::from_archive(archive)),
             }
         }
@@ -102,13 +166,7 @@ impl Archive {
 
             match ffi::archive_read_open_filename(archive, path, block_size) {
                 ffi::fix::ARCHIVE_OK => {
-                    let archive = Archive {
-                        underlying: archive,
-                        block_size,
-                        close_read: true,
-                    };
-
-                    Ok(archive)
+                    Ok(Archive::from_raw_read(archive, block_size))
                 }
 
                 _ => Err(Error::from_archive(archive)),
@@ -170,6 +228,112 @@ impl Archive {
         Ok(())
     }
 
+    /// Appends `fs_path` under `archive_path`, preserving everything
+    /// `append_file` drops: permissions, ownership, timestamps, symlinks,
+    /// hardlinks, device/FIFO nodes and extended attributes. Extended
+    /// attributes are copied for regular files only, and only on Linux. Unlike
+    /// `append_file`, this stats `fs_path` itself rather than taking an
+    /// already-open `File`, since non-regular files can't be opened that way.
+    pub fn append_path<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        archive_path: P,
+        fs_path: Q,
+    ) -> Result<()> {
+        use std::os::unix::fs::{FileTypeExt, MetadataExt};
+
+        let fs_path = fs_path.as_ref();
+        let archive_path = archive_path.as_ref();
+
+        let archive_path_c =
+            CString::new(archive_path.to_string_lossy().as_bytes()).unwrap();
+
+        let meta = fs::symlink_metadata(fs_path)?;
+        let file_type = meta.file_type();
+
+        unsafe {
+            let entry = ffi::archive_entry_new();
+            ffi::archive_entry_set_pathname(entry, archive_path_c.as_ptr());
+            copy_stat(entry, &meta);
+
+            let inode = (meta.dev(), meta.ino());
+            let mut data_file = None;
+
+            if file_type.is_symlink() {
+                let target = fs::read_link(fs_path)?;
+                let target =
+                    CString::new(target.to_string_lossy().as_bytes())
+                        .unwrap();
+
+                ffi::archive_entry_set_filetype(entry, ffi::AE_IFLNK);
+                ffi::archive_entry_set_symlink(entry, target.as_ptr());
+            } else if file_type.is_dir() {
+                ffi::archive_entry_set_filetype(entry, ffi::AE_IFDIR);
+            } else if file_type.is_fifo() {
+                ffi::archive_entry_set_filetype(entry, ffi::AE_IFIFO);
+            } else if file_type.is_block_device()
+                || file_type.is_char_device()
+            {
+                let (major, minor) = major_minor(meta.rdev());
+                ffi::archive_entry_set_rdevmajor(entry, major as i32);
+                ffi::archive_entry_set_rdevminor(entry, minor as i32);
+
+                let filetype = if file_type.is_block_device() {
+                    ffi::AE_IFBLK
+                } else {
+                    ffi::AE_IFCHR
+                };
+                ffi::archive_entry_set_filetype(entry, filetype);
+            } else if meta.nlink() > 1 && self.inodes.contains_key(&inode) {
+                let target = &self.inodes[&inode];
+                let target = CString::new(target.as_bytes()).unwrap();
+
+                ffi::archive_entry_set_filetype(entry, ffi::AE_IFREG);
+                ffi::archive_entry_set_hardlink(entry, target.as_ptr());
+            } else {
+                ffi::archive_entry_set_filetype(entry, ffi::AE_IFREG);
+                copy_xattrs(entry, fs_path)?;
+                data_file = Some(File::open(fs_path)?);
+
+                if meta.nlink() > 1 {
+                    self.inodes.insert(
+                        inode,
+                        archive_path.to_string_lossy().into_owned(),
+                    );
+                }
+            }
+
+            match ffi::archive_write_header(self.underlying, entry) {
+                ffi::fix::ARCHIVE_OK => (),
+                _ => {
+                    ffi::archive_entry_free(entry);
+                    return Err(Error::from_archive(self.underlying));
+                }
+            }
+
+            if let Some(mut file) = data_file {
+                let mut buf = vec![0; self.block_size];
+
+                loop {
+                    let nbytes = file.read(&mut buf)?;
+
+                    if nbytes == 0 {
+                        break;
+                    }
+
+                    ffi::archive_write_data(
+                        self.underlying,
+                        buf.as_ptr() as *mut c_void,
+                        nbytes,
+                    );
+                }
+            }
+
+            ffi::archive_entry_free(entry);
+        }
+
+        Ok(())
+    }
+
     pub fn block_size(&self) -> usize {
         self.block_size
     }
@@ -177,6 +341,54 @@ impl Archive {
     pub fn entries(self) -> Entries {
         Entries::new(self)
     }
+
+    /// Extracts every entry to `dest`, creating it if necessary.
+    pub fn extract_to<P: AsRef<Path>>(
+        self,
+        dest: P,
+        options: ExtractOptions,
+    ) -> Result<()> {
+        let dest = dest.as_ref();
+
+        fs::create_dir_all(dest)?;
+
+        unsafe {
+            let writer = new_disk_writer(options)?;
+            let mut current = std::ptr::null_mut();
+
+            loop {
+                let result = ffi::archive_read_next_header(
+                    self.underlying,
+                    &mut current,
+                );
+
+                match result {
+                    0 => (),
+                    ffi::fix::ARCHIVE_EOF => break,
+                    _ => {
+                        ffi::archive_write_free(writer);
+                        return Err(Error::from_archive(self.underlying));
+                    }
+                }
+
+                if let Err(error) = rebase_pathname(current, dest) {
+                    ffi::archive_write_free(writer);
+                    return Err(error);
+                }
+
+                if let Err(error) =
+                    write_entry_to_disk(self.underlying, current, writer)
+                {
+                    ffi::archive_write_free(writer);
+                    return Err(error);
+                }
+            }
+
+            ffi::archive_write_free(writer);
+        }
+
+        Ok(())
+    }
 }
 
 impl Drop for Archive {
@@ -205,6 +417,7 @@ impl IntoIterator for Archive {
 pub struct Entries {
     archive: Archive,
     current: *mut ffi::archive_entry,
+    generation: Rc<Cell<u64>>,
 }
 
 impl Entries {
@@ -212,6 +425,7 @@ impl Entries {
         Entries {
             archive,
             current: std::ptr::null_mut(),
+            generation: Rc::new(Cell::new(0)),
         }
     }
 }
@@ -228,9 +442,16 @@ impl Iterator for Entries {
 
             match result {
                 0 => {
+                    // bump the generation so readers handed out for the
+                    // previous entry stop yielding data
+                    let generation = self.generation.get() + 1;
+                    self.generation.set(generation);
+
                     let entry = Entry {
                         archive: self.archive.underlying,
                         underlying: self.current,
+                        generation: self.generation.clone(),
+                        created_at: generation,
                     };
                     Some(entry)
                 }
@@ -285,6 +506,182 @@ mod tests {
         temp.close().unwrap();
     }
 
+    #[test]
+    fn archive_from_bytes() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let source = temp.child("src");
+        source.create_dir_all().unwrap();
+        source.child("foo").write_str("foo\n").unwrap();
+
+        let tarball = temp.path().join("src.tar.gz");
+
+        let mut cmd = Command::new("bsdtar");
+        cmd.arg("-C").arg(temp.path());
+        cmd.arg("-czf").arg(&tarball);
+        cmd.arg("src");
+        cmd.assert().success();
+
+        let bytes = fs::read(&tarball).unwrap();
+        let archive = Archive::from_bytes(bytes).unwrap();
+
+        let entries: Vec<String> =
+            archive.entries().map(|entry| entry.path()).collect();
+
+        assert!(entries.iter().any(|path| path == "src/foo"));
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn archive_entry_pax_attributes() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let source = temp.child("src");
+        source.create_dir_all().unwrap();
+        source.child("foo").write_str("foo\n").unwrap();
+
+        let tarball = temp.path().join("src.tar");
+
+        let mut cmd = Command::new("bsdtar");
+        cmd.arg("--format").arg("pax");
+        cmd.arg("-C").arg(temp.path());
+        cmd.arg("-cf").arg(&tarball);
+        cmd.arg("src");
+        cmd.assert().success();
+
+        let archive = Archive::open(&tarball).unwrap();
+        let mut checked = false;
+
+        for entry in archive.entries() {
+            if entry.path() == "src/foo" {
+                assert_eq!(4, entry.size());
+                assert_eq!(crate::FileType::Regular, entry.filetype());
+                checked = true;
+            }
+        }
+
+        assert!(checked, "src/foo entry was not found in the archive");
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn archive_read_entry_data() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let source = temp.child("src");
+        source.create_dir_all().unwrap();
+
+        source.child("foo").write_str("foo\n").unwrap();
+
+        let tarball = temp.path().join("src.tar.gz");
+
+        let mut cmd = Command::new("bsdtar");
+        cmd.arg("-C").arg(temp.path());
+        cmd.arg("-czf").arg(&tarball);
+        cmd.arg("src");
+        cmd.assert().success();
+
+        let archive = Archive::open(&tarball).unwrap();
+
+        for mut entry in archive.entries() {
+            if entry.path() == "src/foo" {
+                let mut contents = String::new();
+                entry.data().read_to_string(&mut contents).unwrap();
+                assert_eq!("foo\n", contents);
+            }
+        }
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn archive_extract_to() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let source = temp.child("src");
+        source.create_dir_all().unwrap();
+
+        source.child("foo").write_str("foo\n").unwrap();
+        source.child("bar").write_str("bar\n").unwrap();
+
+        let tarball = temp.path().join("src.tar.gz");
+
+        let mut cmd = Command::new("bsdtar");
+        cmd.arg("-C").arg(temp.path());
+        cmd.arg("-czf").arg(&tarball);
+        cmd.arg("src");
+        cmd.assert().success();
+
+        let archive = Archive::open(&tarball).unwrap();
+        let dest = temp.child("dest");
+
+        archive.extract_to(dest.path(), ExtractOptions::new()).unwrap();
+
+        dest.child("src/foo").assert("foo\n");
+        dest.child("src/bar").assert("bar\n");
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn archive_extract_to_rejects_absolute_paths() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let source = temp.child("src");
+        source.create_dir_all().unwrap();
+        source.child("foo").write_str("foo\n").unwrap();
+
+        let tarball = temp.path().join("abs.tar");
+
+        let mut cmd = Command::new("tar");
+        cmd.arg("--absolute-names");
+        cmd.arg("--transform").arg("s,^src/foo,/etc/evil-abs,");
+        cmd.arg("-C").arg(temp.path());
+        cmd.arg("-cf").arg(&tarball);
+        cmd.arg("src/foo");
+        cmd.assert().success();
+
+        let archive = Archive::open(&tarball).unwrap();
+        let dest = temp.child("dest");
+
+        let result = archive.extract_to(dest.path(), ExtractOptions::new());
+
+        assert!(result.is_err());
+        assert!(!Path::new("/etc/evil-abs").exists());
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn archive_extract_to_rejects_parent_traversal() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let source = temp.child("src");
+        source.create_dir_all().unwrap();
+        source.child("foo").write_str("foo\n").unwrap();
+
+        let tarball = temp.path().join("dotdot.tar");
+
+        let mut cmd = Command::new("tar");
+        cmd.arg("--transform").arg("s,^src/foo,../evil-dotdot,");
+        cmd.arg("-C").arg(temp.path());
+        cmd.arg("-cf").arg(&tarball);
+        cmd.arg("src/foo");
+        cmd.assert().success();
+
+        let archive = Archive::open(&tarball).unwrap();
+        let dest = temp.child("dest");
+
+        let result = archive.extract_to(dest.path(), ExtractOptions::new());
+
+        assert!(result.is_err());
+        assert!(!temp.path().join("evil-dotdot").exists());
+
+        temp.close().unwrap();
+    }
+
     #[test]
     fn archive_append_file() {
         let temp = assert_fs::TempDir::new().unwrap();
@@ -332,4 +729,153 @@ mod tests {
 
         temp.close().unwrap();
     }
+
+    #[test]
+    fn archive_append_path_preserves_symlinks() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let source = temp.child("src");
+        source.create_dir_all().unwrap();
+
+        let foo = source.child("foo");
+        foo.write_str("foo\n").unwrap();
+
+        let link = source.child("link-to-foo");
+        std::os::unix::fs::symlink(foo.path(), link.path()).unwrap();
+
+        let tarball = temp.path().join("src.tar.gz");
+
+        let mut archive = Archive::create(&tarball).unwrap();
+
+        archive.append_path("src/foo", foo.path()).unwrap();
+        archive.append_path("src/link-to-foo", link.path()).unwrap();
+
+        drop(archive);
+
+        Command::new("bsdtar")
+            .arg("-tvzf")
+            .arg(&tarball)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("src/link-to-foo -> "));
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn archive_append_path_preserves_hardlinks() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let source = temp.child("src");
+        source.create_dir_all().unwrap();
+
+        let foo = source.child("foo");
+        foo.write_str("foo\n").unwrap();
+
+        let bar = source.child("bar");
+        fs::hard_link(foo.path(), bar.path()).unwrap();
+
+        let tarball = temp.path().join("src.tar");
+
+        let mut archive = Archive::create(&tarball).unwrap();
+        archive.append_path("src/foo", foo.path()).unwrap();
+        archive.append_path("src/bar", bar.path()).unwrap();
+        drop(archive);
+
+        let archive = Archive::open(&tarball).unwrap();
+        let mut found_hardlink = false;
+
+        for entry in archive.entries() {
+            if entry.path() == "src/bar" {
+                assert_eq!(Some(String::from("src/foo")), entry.hardlink());
+                found_hardlink = true;
+            }
+        }
+
+        assert!(found_hardlink, "src/bar entry was not found");
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn archive_append_path_preserves_fifo() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let source = temp.child("src");
+        source.create_dir_all().unwrap();
+
+        let fifo = source.path().join("fifo");
+        Command::new("mkfifo").arg(&fifo).assert().success();
+
+        let tarball = temp.path().join("src.tar");
+
+        let mut archive = Archive::create(&tarball).unwrap();
+        archive.append_path("src/fifo", &fifo).unwrap();
+        drop(archive);
+
+        let archive = Archive::open(&tarball).unwrap();
+        let mut found_fifo = false;
+
+        for entry in archive.entries() {
+            if entry.path() == "src/fifo" {
+                assert_eq!(crate::FileType::Fifo, entry.filetype());
+                found_fifo = true;
+            }
+        }
+
+        assert!(found_fifo, "src/fifo entry was not found");
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn archive_append_path_preserves_xattrs() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let source = temp.child("src");
+        source.create_dir_all().unwrap();
+
+        let foo = source.child("foo");
+        foo.write_str("foo\n").unwrap();
+
+        let name = CString::new("user.libarchive_rs_test").unwrap();
+        let value = b"hello";
+        let path = CString::new(foo.path().to_string_lossy().as_bytes())
+            .unwrap();
+
+        let result = unsafe {
+            libc::setxattr(
+                path.as_ptr(),
+                name.as_ptr(),
+                value.as_ptr() as *const c_void,
+                value.len(),
+                0,
+            )
+        };
+        assert_eq!(0, result, "setxattr failed; does this filesystem support xattrs?");
+
+        let tarball = temp.path().join("src.tar");
+
+        let mut archive = Archive::create(&tarball).unwrap();
+        archive.append_path("src/foo", foo.path()).unwrap();
+        drop(archive);
+
+        let archive = Archive::open(&tarball).unwrap();
+        let mut found_xattr = false;
+
+        for mut entry in archive.entries() {
+            if entry.path() == "src/foo" {
+                let xattrs: Vec<(String, Vec<u8>)> = entry.xattrs().collect();
+                assert!(xattrs
+                    .iter()
+                    .any(|(name, value)| name == "user.libarchive_rs_test"
+                        && value == b"hello"));
+                found_xattr = true;
+            }
+        }
+
+        assert!(found_xattr, "src/foo entry was not found");
+
+        temp.close().unwrap();
+    }
 }
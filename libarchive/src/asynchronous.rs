@@ -0,0 +1,350 @@
+//! Tokio-backed async reading, enabled by the `tokio` feature.
+//!
+//! `libarchive` itself is blocking, so this module doesn't reimplement the
+//! FFI loop: it drives the regular [`Archive`]/[`Entries`] on a
+//! `spawn_blocking` thread and forwards headers and decoded blocks to the
+//! async side over bounded channels, so a slow or idle async consumer
+//! applies backpressure all the way down to `archive_read_data` instead of
+//! buffering the whole archive in memory.
+//!
+//! Preserve the invariant that only the current entry may be read: calling
+//! `AsyncEntries::next()` invalidates whatever `AsyncEntry` it previously
+//! handed out, even if the caller is still holding onto it instead of
+//! reading or dropping it. The driver notices via a cancellation flag
+//! rather than blocking on the (possibly full, undrained) data channel
+//! forever.
+
+use std::io::Read;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::{Archive, Result};
+
+struct Header {
+    path: String,
+    data: mpsc::Receiver<std::io::Result<Vec<u8>>>,
+    cancel: Arc<AtomicBool>,
+}
+
+/// Async counterpart of [`Entries`](crate::Entries).
+pub struct AsyncEntries {
+    headers: mpsc::Receiver<Result<Header>>,
+    // the most recently handed-out entry's cancel flag, set when the next
+    // entry is requested so the driver can stop feeding it instead of
+    // blocking on a full, undrained data channel forever
+    current_cancel: Option<Arc<AtomicBool>>,
+    // never awaited; dropping it detaches the blocking task, which exits on
+    // its own once `headers`/the current entry's data channel are dropped
+    _driver: JoinHandle<()>,
+}
+
+impl AsyncEntries {
+    /// Opens `path` and starts walking its entries on a blocking thread.
+    pub fn open<P: AsRef<Path> + Send + 'static>(path: P) -> AsyncEntries {
+        let (headers_tx, headers_rx) = mpsc::channel(1);
+
+        let driver = tokio::task::spawn_blocking(move || {
+            let archive = match Archive::open(path) {
+                Ok(archive) => archive,
+                Err(error) => {
+                    let _ = headers_tx.blocking_send(Err(error));
+                    return;
+                }
+            };
+
+            for mut entry in archive.entries() {
+                let path = entry.path();
+                let (data_tx, data_rx) = mpsc::channel(4);
+                let cancel = Arc::new(AtomicBool::new(false));
+
+                let header = Header {
+                    path,
+                    data: data_rx,
+                    cancel: cancel.clone(),
+                };
+
+                if headers_tx.blocking_send(Ok(header)).is_err() {
+                    // the AsyncEntries was dropped; stop walking the archive
+                    return;
+                }
+
+                let mut reader = entry.data();
+                let mut buffer = vec![0u8; Archive::DEFAULT_BLOCK_SIZE];
+
+                loop {
+                    match reader.read(&mut buffer) {
+                        Ok(0) => break,
+                        Ok(nbytes) => {
+                            let block = buffer[..nbytes].to_vec();
+
+                            if !send_block(&data_tx, Ok(block), &cancel) {
+                                // this entry's reader was dropped, or the
+                                // caller has moved on to a later entry
+                                // without reading it; move on ourselves
+                                break;
+                            }
+                        }
+                        Err(error) => {
+                            let _ = send_block(&data_tx, Err(error), &cancel);
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+
+        AsyncEntries {
+            headers: headers_rx,
+            current_cancel: None,
+            _driver: driver,
+        }
+    }
+
+    /// Awaits the next entry's header. The previously returned
+    /// [`AsyncEntry`], if any, is invalidated at this point whether or not
+    /// it was ever read: the driver notices (via the data channel closing,
+    /// or the cancellation flag this sets) and skips ahead on its own. Blocks
+    /// already queued for the previous entry can still be read from it
+    /// before it reports end of data.
+    pub async fn next(&mut self) -> Option<Result<AsyncEntry>> {
+        if let Some(cancel) = self.current_cancel.take() {
+            cancel.store(true, Ordering::Release);
+        }
+
+        match self.headers.recv().await {
+            Some(Ok(header)) => {
+                self.current_cancel = Some(header.cancel);
+                Some(Ok(AsyncEntry {
+                    path: header.path,
+                    data: header.data,
+                }))
+            }
+            Some(Err(error)) => Some(Err(error)),
+            None => None,
+        }
+    }
+}
+
+// Sends one block through a bounded channel without risking an indefinite
+// block: if the channel stays full, we poll `cancel` between short sleeps
+// instead of parking on `blocking_send` forever, so a caller that requests
+// the next entry without draining or dropping this one can't deadlock the
+// driver thread.
+fn send_block(
+    data_tx: &mpsc::Sender<std::io::Result<Vec<u8>>>,
+    mut block: std::io::Result<Vec<u8>>,
+    cancel: &AtomicBool,
+) -> bool {
+    loop {
+        match data_tx.try_send(block) {
+            Ok(()) => return true,
+            Err(mpsc::error::TrySendError::Closed(_)) => return false,
+            Err(mpsc::error::TrySendError::Full(value)) => {
+                if cancel.load(Ordering::Acquire) {
+                    return false;
+                }
+
+                block = value;
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+    }
+}
+
+/// Async counterpart of [`Entry`](crate::Entry).
+pub struct AsyncEntry {
+    path: String,
+    data: mpsc::Receiver<std::io::Result<Vec<u8>>>,
+}
+
+impl AsyncEntry {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn data(self) -> AsyncEntryReader {
+        AsyncEntryReader {
+            data: self.data,
+            buffer: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+/// Async counterpart of [`EntryReader`](crate::EntryReader).
+pub struct AsyncEntryReader {
+    data: mpsc::Receiver<std::io::Result<Vec<u8>>>,
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl AsyncRead for AsyncEntryReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        loop {
+            if self.position < self.buffer.len() {
+                let available = &self.buffer[self.position..];
+                let nbytes = available.len().min(buf.remaining());
+                buf.put_slice(&available[..nbytes]);
+                self.position += nbytes;
+                return Poll::Ready(Ok(()));
+            }
+
+            match self.data.poll_recv(cx) {
+                Poll::Ready(Some(Ok(block))) => {
+                    self.buffer = block;
+                    self.position = 0;
+                }
+                Poll::Ready(Some(Err(error))) => {
+                    return Poll::Ready(Err(error))
+                }
+                Poll::Ready(None) => return Poll::Ready(Ok(())),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// tests
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_cmd::prelude::*;
+    use assert_fs::prelude::*;
+    use std::process::Command;
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test]
+    async fn async_entries_read_entry_data() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let source = temp.child("src");
+        source.create_dir_all().unwrap();
+        source.child("foo").write_str("foo\n").unwrap();
+        source.child("bar").write_str("bar\n").unwrap();
+
+        let tarball = temp.path().join("src.tar.gz");
+
+        let mut cmd = Command::new("bsdtar");
+        cmd.arg("-C").arg(temp.path());
+        cmd.arg("-czf").arg(&tarball);
+        cmd.arg("src");
+        cmd.assert().success();
+
+        let mut entries = AsyncEntries::open(tarball);
+        let mut seen = Vec::new();
+
+        while let Some(entry) = entries.next().await {
+            let entry = entry.unwrap();
+            let path = entry.path().to_string();
+
+            if path == "src/foo" || path == "src/bar" {
+                let mut contents = Vec::new();
+                entry.data().read_to_end(&mut contents).await.unwrap();
+                seen.push((path, contents));
+            }
+        }
+
+        assert!(seen
+            .iter()
+            .any(|(path, contents)| path == "src/foo"
+                && contents == b"foo\n"));
+        assert!(seen
+            .iter()
+            .any(|(path, contents)| path == "src/bar"
+                && contents == b"bar\n"));
+
+        temp.close().unwrap();
+    }
+
+    #[tokio::test]
+    async fn async_entries_skips_unread_entry() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let source = temp.child("src");
+        source.create_dir_all().unwrap();
+        source.child("foo").write_str("foo\n").unwrap();
+        source.child("bar").write_str("bar\n").unwrap();
+
+        let tarball = temp.path().join("src.tar.gz");
+
+        let mut cmd = Command::new("bsdtar");
+        cmd.arg("-C").arg(temp.path());
+        cmd.arg("-czf").arg(&tarball);
+        cmd.arg("src");
+        cmd.assert().success();
+
+        let mut entries = AsyncEntries::open(tarball);
+
+        // the directory entry ("src/") comes first and is dropped here
+        // without reading its (empty) data
+        let first = entries.next().await.unwrap().unwrap();
+        drop(first);
+
+        let mut paths = Vec::new();
+
+        while let Some(entry) = entries.next().await {
+            paths.push(entry.unwrap().path().to_string());
+        }
+
+        assert!(paths.iter().any(|path| path == "src/foo"));
+        assert!(paths.iter().any(|path| path == "src/bar"));
+
+        temp.close().unwrap();
+    }
+
+    #[tokio::test]
+    async fn async_entries_next_invalidates_unread_entry_without_deadlock() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let source = temp.child("src");
+        source.create_dir_all().unwrap();
+
+        // bigger than the 4-block (4 * DEFAULT_BLOCK_SIZE) data channel
+        // capacity, so holding this entry's header without reading its
+        // data would fill the channel and, prior to the cancellation
+        // fix, wedge the driver thread (and every later `next()`) forever
+        let big = vec![b'x'; 4 * Archive::DEFAULT_BLOCK_SIZE + 1];
+        source.child("big").write_binary(&big).unwrap();
+
+        let tarball = temp.path().join("src.tar.gz");
+
+        let mut cmd = Command::new("bsdtar");
+        cmd.arg("-C").arg(temp.path());
+        cmd.arg("-czf").arg(&tarball);
+        cmd.arg("src");
+        cmd.assert().success();
+
+        let mut entries = AsyncEntries::open(tarball);
+
+        // "src/" itself, dropped without reading
+        drop(entries.next().await.unwrap().unwrap());
+
+        let big_entry = entries.next().await.unwrap().unwrap();
+        assert_eq!(big_entry.path(), "src/big");
+
+        // never call `big_entry.data()`; this must not hang
+        let done = tokio::time::timeout(Duration::from_secs(10), entries.next())
+            .await
+            .expect("AsyncEntries::next() deadlocked on an unread entry");
+
+        assert!(done.is_none());
+
+        drop(big_entry);
+        temp.close().unwrap();
+    }
+}
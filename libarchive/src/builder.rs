@@ -0,0 +1,254 @@
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::path::Path;
+
+use libarchive_sys as ffi;
+
+use crate::format::{Format, ReadFilter, WriteFilter};
+use crate::Archive;
+use crate::Error;
+use crate::Result;
+
+#[derive(Default)]
+pub struct ArchiveReaderBuilder {
+    formats: Vec<Format>,
+    filters: Vec<ReadFilter>,
+}
+
+impl ArchiveReaderBuilder {
+    pub fn new() -> ArchiveReaderBuilder {
+        ArchiveReaderBuilder::default()
+    }
+
+    pub fn format(mut self, format: Format) -> Self {
+        self.formats.push(format);
+        self
+    }
+
+    pub fn filter(mut self, filter: ReadFilter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    pub fn open<P: AsRef<Path>>(self, path: P) -> Result<Archive> {
+        let path = path.as_ref();
+
+        let file = path.to_string_lossy();
+        let file = CString::new(file.as_bytes()).unwrap();
+
+        let block_size = Archive::block_size_of(path)?;
+
+        self.open_filename(file.as_ptr(), block_size)
+    }
+
+    pub fn stdin(self) -> Result<Archive> {
+        self.open_filename(std::ptr::null(), Archive::DEFAULT_BLOCK_SIZE)
+    }
+
+    fn open_filename(
+        self,
+        path: *const c_char,
+        block_size: usize,
+    ) -> Result<Archive> {
+        unsafe {
+            let archive = ffi::archive_read_new();
+
+            if archive.is_null() {
+                return Err(Error::new("archive allocation error"));
+            }
+
+            if self.formats.is_empty() {
+                ffi::archive_read_support_format_all(archive);
+            } else {
+                for format in &self.formats {
+                    match format.support_read(archive) {
+                        ffi::fix::ARCHIVE_OK => (),
+                        _ => return Err(Error::from_archive(archive)),
+                    }
+                }
+            }
+
+            if self.filters.is_empty() {
+                ffi::archive_read_support_filter_all(archive);
+            } else {
+                for filter in &self.filters {
+                    match filter.support(archive) {
+                        ffi::fix::ARCHIVE_OK => (),
+                        _ => return Err(Error::from_archive(archive)),
+                    }
+                }
+            }
+
+            match ffi::archive_read_open_filename(archive, path, block_size) {
+                ffi::fix::ARCHIVE_OK => {
+                    Ok(Archive::from_raw_read(archive, block_size))
+                }
+                _ => Err(Error::from_archive(archive)),
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ArchiveWriterBuilder {
+    format: Option<Format>,
+    filter: Option<WriteFilter>,
+}
+
+impl ArchiveWriterBuilder {
+    pub fn new() -> ArchiveWriterBuilder {
+        ArchiveWriterBuilder::default()
+    }
+
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    pub fn filter(mut self, filter: WriteFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    pub fn create<P: AsRef<Path>>(self, path: P) -> Result<Archive> {
+        let path = path.as_ref();
+
+        let file = path.to_string_lossy();
+        let file = CString::new(file.as_bytes()).unwrap();
+
+        unsafe {
+            let archive = ffi::archive_write_new();
+
+            if archive.is_null() {
+                return Err(Error::new("archive allocation error"));
+            }
+
+            let format_result = match &self.format {
+                Some(format) => format.set_write(archive),
+                None => ffi::archive_write_set_format_filter_by_ext(
+                    archive,
+                    file.as_ptr(),
+                ),
+            };
+
+            match format_result {
+                ffi::fix::ARCHIVE_OK => (),
+                _ => return Err(Error::from_archive(archive)),
+            }
+
+            if let Some(filter) = &self.filter {
+                match filter.add(archive) {
+                    ffi::fix::ARCHIVE_OK => (),
+                    _ => return Err(Error::from_archive(archive)),
+                }
+            }
+
+            match ffi::archive_write_open_filename(archive, file.as_ptr()) {
+                ffi::fix::ARCHIVE_OK => Ok(Archive::from_raw_write(archive)),
+                _ => Err(Error::from_archive(archive)),
+            }
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// tests
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::Format;
+    use assert_cmd::prelude::*;
+    use assert_fs::prelude::*;
+    use std::fs::File;
+    use std::process::Command;
+
+    #[test]
+    fn reader_builder_restricts_format() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let source = temp.child("src");
+        source.create_dir_all().unwrap();
+        source.child("foo").write_str("foo\n").unwrap();
+
+        let tarball = temp.path().join("src.tar");
+
+        let mut cmd = Command::new("bsdtar");
+        cmd.arg("-C").arg(temp.path());
+        cmd.arg("-cf").arg(&tarball);
+        cmd.arg("src");
+        cmd.assert().success();
+
+        let archive = ArchiveReaderBuilder::new()
+            .format(Format::Tar)
+            .open(&tarball)
+            .unwrap();
+
+        let entries: Vec<String> =
+            archive.entries().map(|entry| entry.path()).collect();
+
+        assert!(entries.iter().any(|path| path == "src/foo"));
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn reader_builder_rejects_format_mismatch() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let source = temp.child("src");
+        source.create_dir_all().unwrap();
+        source.child("foo").write_str("foo\n").unwrap();
+
+        let archive_path = temp.path().join("src.zip");
+
+        let mut cmd = Command::new("zip");
+        cmd.arg("-r").arg(&archive_path).arg("src");
+        cmd.current_dir(temp.path());
+        cmd.assert().success();
+
+        let result = ArchiveReaderBuilder::new()
+            .format(Format::Tar)
+            .open(&archive_path);
+
+        assert!(result.is_err());
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn writer_builder_round_trips_format_and_filter() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let source = temp.child("src");
+        source.create_dir_all().unwrap();
+        let foo = source.child("foo");
+        foo.write_str("foo\n").unwrap();
+
+        let archive_path = temp.path().join("src.tar.gz");
+
+        let mut archive = ArchiveWriterBuilder::new()
+            .format(Format::Tar)
+            .filter(WriteFilter::Gzip)
+            .create(&archive_path)
+            .unwrap();
+
+        let mut file = File::open(foo.path()).unwrap();
+        archive.append_file("src/foo", &mut file).unwrap();
+        drop(archive);
+
+        let archive = ArchiveReaderBuilder::new()
+            .format(Format::Tar)
+            .filter(ReadFilter::Gzip)
+            .open(&archive_path)
+            .unwrap();
+
+        let entries: Vec<String> =
+            archive.entries().map(|entry| entry.path()).collect();
+
+        assert!(entries.iter().any(|path| path == "src/foo"));
+
+        temp.close().unwrap();
+    }
+}
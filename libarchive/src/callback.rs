@@ -0,0 +1,70 @@
+use std::ffi::CString;
+use std::io::Read;
+use std::os::raw::{c_int, c_void};
+
+use libarchive_sys as ffi;
+
+// boxed reader plus the buffer libarchive reads blocks back through
+struct CallbackState<R> {
+    reader: R,
+    buffer: Vec<u8>,
+}
+
+unsafe extern "C" fn read_callback<R: Read>(
+    archive: *mut ffi::archive,
+    client_data: *mut c_void,
+    buffer: *mut *const c_void,
+) -> ffi::la_ssize_t {
+    let state = &mut *(client_data as *mut CallbackState<R>);
+
+    match state.reader.read(&mut state.buffer) {
+        Ok(nbytes) => {
+            *buffer = state.buffer.as_ptr() as *const c_void;
+            nbytes as ffi::la_ssize_t
+        }
+        Err(error) => {
+            let fmt = CString::new("%s").unwrap();
+            let message = CString::new(format!("{}", error))
+                .unwrap_or_else(|_| {
+                    CString::new("read callback error").unwrap()
+                });
+
+            ffi::archive_set_error(
+                archive,
+                ffi::fix::ARCHIVE_ERRNO_MISC,
+                fmt.as_ptr(),
+                message.as_ptr(),
+            );
+
+            -1
+        }
+    }
+}
+
+unsafe extern "C" fn close_callback<R>(
+    _archive: *mut ffi::archive,
+    client_data: *mut c_void,
+) -> c_int {
+    drop(Box::from_raw(client_data as *mut CallbackState<R>));
+    ffi::fix::ARCHIVE_OK
+}
+
+// boxes `reader` as the callback client data; freed by close_callback when
+// archive_read_close/archive_read_free runs
+pub(crate) unsafe fn open_with_reader<R: Read + 'static>(
+    archive: *mut ffi::archive,
+    reader: R,
+    block_size: usize,
+) -> c_int {
+    let state = Box::new(CallbackState {
+        reader,
+        buffer: vec![0; block_size],
+    });
+    let client_data = Box::into_raw(state) as *mut c_void;
+
+    ffi::archive_read_set_callback_data(archive, client_data);
+    ffi::archive_read_set_read_callback(archive, Some(read_callback::<R>));
+    ffi::archive_read_set_close_callback(archive, Some(close_callback::<R>));
+
+    ffi::archive_read_open1(archive)
+}
@@ -0,0 +1,274 @@
+use std::cell::Cell;
+use std::ffi::CStr;
+use std::io::{self, Read};
+use std::os::raw::{c_char, c_void};
+use std::path::Path;
+use std::rc::Rc;
+
+use libarchive_sys as ffi;
+
+use crate::extract::{new_disk_writer, rebase_pathname, write_entry_to_disk};
+use crate::Error;
+use crate::ExtractOptions;
+use crate::Result;
+
+pub struct Entry {
+    pub(crate) archive: *mut ffi::archive,
+    pub(crate) underlying: *mut ffi::archive_entry,
+    pub(crate) generation: Rc<Cell<u64>>,
+    pub(crate) created_at: u64,
+}
+
+/// The kind of filesystem object an entry represents, as parsed out of its
+/// header (`AE_IF*`/PAX `typeflag`).
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FileType {
+    Regular,
+    Directory,
+    Symlink,
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
+    Unknown,
+}
+
+impl Entry {
+    pub fn path(&self) -> String {
+        unsafe {
+            let path = ffi::archive_entry_pathname(self.underlying);
+
+            if path.is_null() {
+                return String::new();
+            }
+
+            CStr::from_ptr(path).to_string_lossy().into_owned()
+        }
+    }
+
+    /// Uncompressed size in bytes, as recorded in the header (or the PAX
+    /// `size` attribute for files too large for the base format's field).
+    pub fn size(&self) -> i64 {
+        unsafe { ffi::archive_entry_size(self.underlying) }
+    }
+
+    /// Modification time as `(seconds, nanoseconds)` since the epoch. The
+    /// sub-second component only has meaningful precision for formats that
+    /// carry a PAX `mtime` attribute.
+    pub fn mtime(&self) -> (i64, i64) {
+        unsafe {
+            (
+                ffi::archive_entry_mtime(self.underlying),
+                ffi::archive_entry_mtime_nsec(self.underlying),
+            )
+        }
+    }
+
+    pub fn uid(&self) -> i64 {
+        unsafe { ffi::archive_entry_uid(self.underlying) }
+    }
+
+    pub fn gid(&self) -> i64 {
+        unsafe { ffi::archive_entry_gid(self.underlying) }
+    }
+
+    pub fn uname(&self) -> Option<String> {
+        unsafe { optional_string(ffi::archive_entry_uname(self.underlying)) }
+    }
+
+    pub fn gname(&self) -> Option<String> {
+        unsafe { optional_string(ffi::archive_entry_gname(self.underlying)) }
+    }
+
+    /// Permission bits, as in `st_mode & 0o7777`.
+    pub fn mode(&self) -> u32 {
+        unsafe { ffi::archive_entry_perm(self.underlying) }
+    }
+
+    pub fn filetype(&self) -> FileType {
+        unsafe {
+            match ffi::archive_entry_filetype(self.underlying) {
+                ffi::AE_IFREG => FileType::Regular,
+                ffi::AE_IFDIR => FileType::Directory,
+                ffi::AE_IFLNK => FileType::Symlink,
+                ffi::AE_IFIFO => FileType::Fifo,
+                ffi::AE_IFSOCK => FileType::Socket,
+                ffi::AE_IFBLK => FileType::BlockDevice,
+                ffi::AE_IFCHR => FileType::CharDevice,
+                _ => FileType::Unknown,
+            }
+        }
+    }
+
+    /// The link target, for `FileType::Symlink` entries.
+    pub fn symlink(&self) -> Option<String> {
+        unsafe {
+            optional_string(ffi::archive_entry_symlink(self.underlying))
+        }
+    }
+
+    /// The target of the already-appended entry this one hardlinks to.
+    pub fn hardlink(&self) -> Option<String> {
+        unsafe {
+            optional_string(ffi::archive_entry_hardlink(self.underlying))
+        }
+    }
+
+    /// Iterates over the raw extended attributes libarchive parsed out of
+    /// the header (PAX `SCHILY.xattr.*`, and their equivalents in other
+    /// formats). Takes `&mut self` because the iteration cursor lives on
+    /// the underlying `archive_entry`, not in `Xattrs` itself: a second
+    /// `xattrs()` call would rewind a still-live iterator.
+    pub fn xattrs(&mut self) -> Xattrs<'_> {
+        unsafe {
+            let remaining = ffi::archive_entry_xattr_count(self.underlying);
+            ffi::archive_entry_xattr_reset(self.underlying);
+            Xattrs {
+                entry: self,
+                remaining,
+            }
+        }
+    }
+
+    /// Returns a reader over this entry's data. Only one reader may be
+    /// outstanding for the current entry at a time; calling `next()` on the
+    /// `Entries` this entry came from invalidates it, after which it reads
+    /// as empty instead of bleeding into the next entry's data.
+    pub fn data(&mut self) -> EntryReader {
+        EntryReader {
+            archive: self.archive,
+            generation: self.generation.clone(),
+            created_at: self.created_at,
+        }
+    }
+
+    fn is_current(&self) -> bool {
+        self.generation.get() == self.created_at
+    }
+
+    /// Extracts this single entry to `dest`, creating it if necessary.
+    pub fn unpack_in<P: AsRef<Path>>(
+        &mut self,
+        dest: P,
+        options: ExtractOptions,
+    ) -> Result<()> {
+        if !self.is_current() {
+            return Err(Error::new(
+                "entry is stale: Entries::next() has already moved past it",
+            ));
+        }
+
+        let dest = dest.as_ref();
+
+        std::fs::create_dir_all(dest)?;
+
+        unsafe {
+            let writer = new_disk_writer(options)?;
+
+            rebase_pathname(self.underlying, dest)?;
+
+            let result =
+                write_entry_to_disk(self.archive, self.underlying, writer);
+
+            ffi::archive_write_free(writer);
+
+            result
+        }
+    }
+}
+
+pub struct EntryReader {
+    archive: *mut ffi::archive,
+    generation: Rc<Cell<u64>>,
+    created_at: u64,
+}
+
+impl EntryReader {
+    fn is_current(&self) -> bool {
+        self.generation.get() == self.created_at
+    }
+}
+
+impl Read for EntryReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.is_current() {
+            return Ok(0);
+        }
+
+        unsafe {
+            let nbytes = ffi::archive_read_data(
+                self.archive,
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len(),
+            );
+
+            if nbytes < 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    Error::from_archive(self.archive),
+                ));
+            }
+
+            Ok(nbytes as usize)
+        }
+    }
+}
+
+unsafe fn optional_string(ptr: *const c_char) -> Option<String> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(ptr).to_string_lossy().into_owned())
+    }
+}
+
+/// Iterator over an entry's raw extended attributes, yielded as
+/// `(name, value)` pairs. See `Entry::xattrs`.
+pub struct Xattrs<'a> {
+    entry: &'a mut Entry,
+    remaining: i32,
+}
+
+impl<'a> Iterator for Xattrs<'a> {
+    type Item = (String, Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining <= 0 {
+            return None;
+        }
+
+        unsafe {
+            let mut name: *const c_char = std::ptr::null();
+            let mut value: *const c_void = std::ptr::null();
+            let mut size: usize = 0;
+
+            let result = ffi::archive_entry_xattr_next(
+                self.entry.underlying,
+                &mut name,
+                &mut value,
+                &mut size,
+            );
+
+            match result {
+                ffi::fix::ARCHIVE_OK => {
+                    self.remaining -= 1;
+
+                    let name =
+                        CStr::from_ptr(name).to_string_lossy().into_owned();
+                    let value = if value.is_null() {
+                        Vec::new()
+                    } else {
+                        std::slice::from_raw_parts(value as *const u8, size)
+                            .to_vec()
+                    };
+
+                    Some((name, value))
+                }
+                _ => {
+                    self.remaining = 0;
+                    None
+                }
+            }
+        }
+    }
+}
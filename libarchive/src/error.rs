@@ -27,6 +27,11 @@ impl Error {
     pub(crate) fn from_archive(archive: *mut ffi::archive) -> Error {
         unsafe {
             let msg = ffi::archive_error_string(archive);
+
+            if msg.is_null() {
+                return Error::new("unknown libarchive error");
+            }
+
             let msg = CStr::from_ptr(msg);
             let msg = msg.to_string_lossy();
             Error::new(&msg)
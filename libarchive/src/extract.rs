@@ -0,0 +1,189 @@
+use std::path::Path;
+
+use libarchive_sys as ffi;
+
+use crate::Error;
+use crate::Result;
+
+// path-traversal protection is on by default
+#[derive(Clone, Copy, Debug)]
+pub struct ExtractOptions {
+    overwrite: bool,
+    restore_permissions: bool,
+    restore_times: bool,
+    restore_owner: bool,
+    secure_no_absolute_paths: bool,
+    secure_no_parent_refs: bool,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> ExtractOptions {
+        ExtractOptions {
+            overwrite: false,
+            restore_permissions: true,
+            restore_times: true,
+            restore_owner: true,
+            secure_no_absolute_paths: true,
+            secure_no_parent_refs: true,
+        }
+    }
+}
+
+impl ExtractOptions {
+    pub fn new() -> ExtractOptions {
+        ExtractOptions::default()
+    }
+
+    pub fn overwrite(mut self, overwrite: bool) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    pub fn restore_permissions(mut self, restore: bool) -> Self {
+        self.restore_permissions = restore;
+        self
+    }
+
+    pub fn restore_times(mut self, restore: bool) -> Self {
+        self.restore_times = restore;
+        self
+    }
+
+    pub fn restore_owner(mut self, restore: bool) -> Self {
+        self.restore_owner = restore;
+        self
+    }
+
+    pub fn secure_no_absolute_paths(mut self, secure: bool) -> Self {
+        self.secure_no_absolute_paths = secure;
+        self
+    }
+
+    pub fn secure_no_parent_refs(mut self, secure: bool) -> Self {
+        self.secure_no_parent_refs = secure;
+        self
+    }
+
+    pub(crate) fn flags(&self) -> i32 {
+        let mut flags = 0;
+
+        if !self.overwrite {
+            flags |= ffi::fix::ARCHIVE_EXTRACT_NO_OVERWRITE;
+        }
+
+        if self.restore_permissions {
+            flags |= ffi::fix::ARCHIVE_EXTRACT_PERM;
+        }
+
+        if self.restore_times {
+            flags |= ffi::fix::ARCHIVE_EXTRACT_TIME;
+        }
+
+        if self.restore_owner {
+            flags |= ffi::fix::ARCHIVE_EXTRACT_OWNER;
+        }
+
+        if self.secure_no_absolute_paths {
+            flags |= ffi::fix::ARCHIVE_EXTRACT_SECURE_NOABSOLUTEPATHS;
+        }
+
+        if self.secure_no_parent_refs {
+            flags |= ffi::fix::ARCHIVE_EXTRACT_SECURE_NODOTDOT;
+        }
+
+        flags
+    }
+}
+
+// shared by Archive::extract_to (one writer reused across entries) and
+// Entry::unpack_in (a throwaway writer for a single entry)
+pub(crate) fn write_entry_to_disk(
+    reader: *mut ffi::archive,
+    entry: *mut ffi::archive_entry,
+    writer: *mut ffi::archive,
+) -> Result<()> {
+    unsafe {
+        match ffi::archive_write_header(writer, entry) {
+            ffi::fix::ARCHIVE_OK => (),
+            _ => return Err(Error::from_archive(writer)),
+        }
+
+        loop {
+            let mut buffer: *const std::os::raw::c_void = std::ptr::null();
+            let mut size: usize = 0;
+            let mut offset: i64 = 0;
+
+            let result = ffi::archive_read_data_block(
+                reader,
+                &mut buffer,
+                &mut size,
+                &mut offset,
+            );
+
+            match result {
+                ffi::fix::ARCHIVE_EOF => break,
+                ffi::fix::ARCHIVE_OK => (),
+                _ => return Err(Error::from_archive(reader)),
+            }
+
+            if ffi::archive_write_data_block(writer, buffer, size, offset)
+                < 0
+            {
+                return Err(Error::from_archive(writer));
+            }
+        }
+
+        match ffi::archive_write_finish_entry(writer) {
+            ffi::fix::ARCHIVE_OK => Ok(()),
+            _ => Err(Error::from_archive(writer)),
+        }
+    }
+}
+
+pub(crate) fn new_disk_writer(
+    options: ExtractOptions,
+) -> Result<*mut ffi::archive> {
+    unsafe {
+        let writer = ffi::archive_write_disk_new();
+
+        if writer.is_null() {
+            return Err(Error::new("archive allocation error"));
+        }
+
+        match ffi::archive_write_disk_set_options(writer, options.flags()) {
+            ffi::fix::ARCHIVE_OK => (),
+            _ => return Err(Error::from_archive(writer)),
+        }
+
+        ffi::archive_write_disk_set_standard_lookup(writer);
+
+        Ok(writer)
+    }
+}
+
+// joins entry's pathname onto dest; an absolute pathname replaces dest
+// (`Path::join`) and `..` components are kept, so those are left to the
+// secure_* extract flags
+pub(crate) fn rebase_pathname(
+    entry: *mut ffi::archive_entry,
+    dest: &Path,
+) -> Result<()> {
+    use std::ffi::{CStr, CString};
+
+    unsafe {
+        let pathname = ffi::archive_entry_pathname(entry);
+
+        if pathname.is_null() {
+            return Err(Error::new("entry has no pathname"));
+        }
+
+        let pathname = CStr::from_ptr(pathname).to_string_lossy();
+        let joined = dest.join(pathname.as_ref());
+        let joined = joined.to_string_lossy();
+        let joined = CString::new(joined.as_bytes()).unwrap();
+
+        ffi::archive_entry_set_pathname(entry, joined.as_ptr());
+
+        Ok(())
+    }
+}
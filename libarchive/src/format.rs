@@ -0,0 +1,148 @@
+use std::ffi::CString;
+use std::os::raw::c_void;
+
+use libarchive_sys as ffi;
+
+/// Archive formats recognized when reading, or produced when writing.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Format {
+    Tar,
+    Gnutar,
+    Zip,
+    SevenZip,
+    Cpio,
+    Iso9660,
+    Mtree,
+    Raw,
+    Ar,
+}
+
+impl Format {
+    pub(crate) unsafe fn support_read(&self, archive: *mut ffi::archive) -> i32 {
+        match self {
+            Format::Tar => ffi::archive_read_support_format_tar(archive),
+            Format::Gnutar => ffi::archive_read_support_format_gnutar(archive),
+            Format::Zip => ffi::archive_read_support_format_zip(archive),
+            Format::SevenZip => ffi::archive_read_support_format_7zip(archive),
+            Format::Cpio => ffi::archive_read_support_format_cpio(archive),
+            Format::Iso9660 => {
+                ffi::archive_read_support_format_iso9660(archive)
+            }
+            Format::Mtree => ffi::archive_read_support_format_mtree(archive),
+            Format::Raw => ffi::archive_read_support_format_raw(archive),
+            Format::Ar => ffi::archive_read_support_format_ar(archive),
+        }
+    }
+
+    pub(crate) unsafe fn set_write(&self, archive: *mut ffi::archive) -> i32 {
+        match self {
+            Format::Tar => {
+                ffi::archive_write_set_format_pax_restricted(archive)
+            }
+            Format::Gnutar => ffi::archive_write_set_format_gnutar(archive),
+            Format::Zip => ffi::archive_write_set_format_zip(archive),
+            Format::SevenZip => ffi::archive_write_set_format_7zip(archive),
+            Format::Cpio => ffi::archive_write_set_format_cpio(archive),
+            Format::Iso9660 => ffi::archive_write_set_format_iso9660(archive),
+            Format::Mtree => ffi::archive_write_set_format_mtree(archive),
+            Format::Raw => ffi::archive_write_set_format_raw(archive),
+            Format::Ar => ffi::archive_write_set_format_ar_bsd(archive),
+        }
+    }
+}
+
+/// Decompression filters recognized when reading an archive.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ReadFilter {
+    Gzip,
+    Bzip2,
+    Xz,
+    Lzma,
+    Lzip,
+    Lzop,
+    Zstd,
+    Compress,
+    Uu,
+    Rpm,
+    None,
+    Program(String),
+    ProgramSignature(String, Vec<u8>),
+}
+
+impl ReadFilter {
+    pub(crate) unsafe fn support(&self, archive: *mut ffi::archive) -> i32 {
+        match self {
+            ReadFilter::Gzip => ffi::archive_read_support_filter_gzip(archive),
+            ReadFilter::Bzip2 => {
+                ffi::archive_read_support_filter_bzip2(archive)
+            }
+            ReadFilter::Xz => ffi::archive_read_support_filter_xz(archive),
+            ReadFilter::Lzma => ffi::archive_read_support_filter_lzma(archive),
+            ReadFilter::Lzip => ffi::archive_read_support_filter_lzip(archive),
+            ReadFilter::Lzop => ffi::archive_read_support_filter_lzop(archive),
+            ReadFilter::Zstd => ffi::archive_read_support_filter_zstd(archive),
+            ReadFilter::Compress => {
+                ffi::archive_read_support_filter_compress(archive)
+            }
+            ReadFilter::Uu => ffi::archive_read_support_filter_uu(archive),
+            ReadFilter::Rpm => ffi::archive_read_support_filter_rpm(archive),
+            ReadFilter::None => ffi::archive_read_support_filter_none(archive),
+            ReadFilter::Program(command) => {
+                let command = CString::new(command.as_bytes()).unwrap();
+                ffi::archive_read_support_filter_program(
+                    archive,
+                    command.as_ptr(),
+                )
+            }
+            ReadFilter::ProgramSignature(command, signature) => {
+                let command = CString::new(command.as_bytes()).unwrap();
+                ffi::archive_read_support_filter_program_signature(
+                    archive,
+                    command.as_ptr(),
+                    signature.as_ptr() as *mut c_void,
+                    signature.len(),
+                )
+            }
+        }
+    }
+}
+
+/// Compression filters applied when writing an archive.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum WriteFilter {
+    Gzip,
+    Bzip2,
+    Xz,
+    Lzma,
+    Lzip,
+    Lzop,
+    Zstd,
+    Compress,
+    None,
+    Program(String),
+}
+
+impl WriteFilter {
+    pub(crate) unsafe fn add(&self, archive: *mut ffi::archive) -> i32 {
+        match self {
+            WriteFilter::Gzip => ffi::archive_write_add_filter_gzip(archive),
+            WriteFilter::Bzip2 => ffi::archive_write_add_filter_bzip2(archive),
+            WriteFilter::Xz => ffi::archive_write_add_filter_xz(archive),
+            WriteFilter::Lzma => ffi::archive_write_add_filter_lzma(archive),
+            WriteFilter::Lzip => ffi::archive_write_add_filter_lzip(archive),
+            WriteFilter::Lzop => ffi::archive_write_add_filter_lzop(archive),
+            WriteFilter::Zstd => ffi::archive_write_add_filter_zstd(archive),
+            WriteFilter::Compress => {
+                ffi::archive_write_add_filter_compress(archive)
+            }
+            WriteFilter::None => ffi::archive_write_add_filter_none(archive),
+            WriteFilter::Program(command) => {
+                let command = CString::new(command.as_bytes()).unwrap();
+                ffi::archive_write_add_filter_program(
+                    archive,
+                    command.as_ptr(),
+                )
+            }
+        }
+    }
+}
@@ -0,0 +1,19 @@
+mod archive;
+#[cfg(feature = "tokio")]
+mod asynchronous;
+mod builder;
+mod callback;
+mod entry;
+mod error;
+mod extract;
+mod format;
+mod metadata;
+
+pub use crate::archive::{Archive, Entries};
+#[cfg(feature = "tokio")]
+pub use crate::asynchronous::{AsyncEntries, AsyncEntry, AsyncEntryReader};
+pub use crate::builder::{ArchiveReaderBuilder, ArchiveWriterBuilder};
+pub use crate::entry::{Entry, EntryReader, FileType, Xattrs};
+pub use crate::error::{Error, ErrorKind, Result};
+pub use crate::extract::ExtractOptions;
+pub use crate::format::{Format, ReadFilter, WriteFilter};
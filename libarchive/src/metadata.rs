@@ -0,0 +1,101 @@
+use std::ffi::CString;
+use std::fs::Metadata;
+use std::os::raw::c_void;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+use libarchive_sys as ffi;
+
+use crate::Result;
+
+pub(crate) fn copy_stat(entry: *mut ffi::archive_entry, meta: &Metadata) {
+    unsafe {
+        ffi::archive_entry_set_perm(entry, meta.mode());
+        ffi::archive_entry_set_uid(entry, meta.uid() as i64);
+        ffi::archive_entry_set_gid(entry, meta.gid() as i64);
+        ffi::archive_entry_set_size(entry, meta.size() as i64);
+        ffi::archive_entry_set_mtime(entry, meta.mtime(), meta.mtime_nsec());
+        ffi::archive_entry_set_atime(entry, meta.atime(), meta.atime_nsec());
+        ffi::archive_entry_set_ctime(entry, meta.ctime(), meta.ctime_nsec());
+    }
+}
+
+// major(3)/minor(3) as glibc defines them
+pub(crate) fn major_minor(rdev: u64) -> (u32, u32) {
+    let major = ((rdev >> 8) & 0xfff) | ((rdev >> 32) & !0xfff);
+    let minor = (rdev & 0xff) | ((rdev >> 12) & !0xff);
+    (major as u32, minor as u32)
+}
+
+// listxattr/getxattr aren't portable across the BSDs/macOS
+#[cfg(target_os = "linux")]
+pub(crate) fn copy_xattrs(
+    entry: *mut ffi::archive_entry,
+    path: &Path,
+) -> Result<()> {
+    let path = CString::new(path.as_os_str().to_string_lossy().as_bytes())
+        .unwrap();
+
+    unsafe {
+        let list_size = libc::listxattr(path.as_ptr(), std::ptr::null_mut(), 0);
+
+        if list_size <= 0 {
+            return Ok(());
+        }
+
+        let mut names = vec![0u8; list_size as usize];
+        let list_size = libc::listxattr(
+            path.as_ptr(),
+            names.as_mut_ptr() as *mut i8,
+            names.len(),
+        );
+
+        if list_size <= 0 {
+            return Ok(());
+        }
+
+        names.truncate(list_size as usize);
+
+        for name in names.split(|&byte| byte == 0).filter(|n| !n.is_empty()) {
+            let name = CString::new(name).unwrap();
+
+            let value_size =
+                libc::getxattr(path.as_ptr(), name.as_ptr(), std::ptr::null_mut(), 0);
+
+            if value_size < 0 {
+                continue;
+            }
+
+            let mut value = vec![0u8; value_size as usize];
+            let value_size = libc::getxattr(
+                path.as_ptr(),
+                name.as_ptr(),
+                value.as_mut_ptr() as *mut c_void,
+                value.len(),
+            );
+
+            if value_size < 0 {
+                continue;
+            }
+
+            value.truncate(value_size as usize);
+
+            ffi::archive_entry_xattr_add_entry(
+                entry,
+                name.as_ptr(),
+                value.as_ptr() as *const c_void,
+                value.len(),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn copy_xattrs(
+    _entry: *mut ffi::archive_entry,
+    _path: &Path,
+) -> Result<()> {
+    Ok(())
+}